@@ -0,0 +1,192 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::Bowl;
+
+pub type BlobHash = [u8; 32];
+
+/// Implemented by media types whose file bytes should be deduplicated on
+/// disk by content hash, alongside the metadata `Bowl` already tracks.
+pub trait Blob {
+    fn blob_bytes(&self) -> &[u8];
+}
+
+fn hash_of(bytes: &[u8]) -> BlobHash {
+    Sha256::digest(bytes).into()
+}
+
+fn hex(hash: &BlobHash) -> String {
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// One entry per uuid that currently references a blob, so `delete` can
+// release the reference without requiring every `T` passed to it to be
+// `Blob` (Rust has no specialization, so this is how `add`/`delete` stay
+// generic over non-blob types too).
+pub(crate) type BlobRefs = HashMap<(TypeId, String, String), BlobHash>;
+
+/// Content-addressed store for the underlying media bytes. Entries are
+/// written to `<root>/<hash-prefix>/<hash>` only if absent, so many uuids
+/// can reference one physical blob.
+#[derive(Debug)]
+pub struct BlobStore {
+    root: PathBuf,
+    refcounts: RwLock<HashMap<BlobHash, usize>>,
+}
+
+impl BlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            refcounts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn path_for(&self, hash: &BlobHash) -> PathBuf {
+        let hex = hex(hash);
+        self.root.join(&hex[..2]).join(hex)
+    }
+
+    // Writes the blob to disk if it isn't already there. Whether this is a
+    // *new reference* is a separate question, decided by the caller via
+    // `acquire`/`release` — writing happens purely based on on-disk
+    // presence so a failed write never leaves a refcount pointing at bytes
+    // that were never actually stored.
+    async fn write_if_absent(&self, bytes: &[u8]) -> std::io::Result<BlobHash> {
+        let hash = hash_of(bytes);
+        let path = self.path_for(&hash);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        if tokio::fs::metadata(&path).await.is_err() {
+            let written = async {
+                let mut file = File::create(&path).await?;
+                file.write_all(bytes).await?;
+                file.flush().await?;
+                file.sync_all().await
+            }
+            .await;
+            // A partial file would otherwise pass the presence check above
+            // on the next call and be treated as a stored blob.
+            if let Err(e) = written {
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(e);
+            }
+        }
+        Ok(hash)
+    }
+
+    // Registers one more logical reference to `hash`. Only call this once
+    // per distinct `(type, org, uuid)` -> `hash` binding, after the blob has
+    // been durably written — never per `add_with_blob` call, since the same
+    // entry can be re-added with unchanged bytes many times.
+    fn acquire(&self, hash: BlobHash) {
+        *self.refcounts.write().entry(hash).or_insert(0) += 1;
+    }
+
+    // Blocking on purpose: `Bowl::delete` is a sync API, so releasing the
+    // blob a deleted entry referenced happens inline rather than spawning
+    // an async task the caller has no handle on.
+    fn release(&self, hash: BlobHash) -> std::io::Result<()> {
+        let last_ref = {
+            let mut refcounts = self.refcounts.write();
+            match refcounts.get_mut(&hash) {
+                Some(count) => {
+                    *count -= 1;
+                    let last = *count == 0;
+                    if last {
+                        refcounts.remove(&hash);
+                    }
+                    last
+                }
+                None => false,
+            }
+        };
+        if last_ref {
+            let path = self.path_for(&hash);
+            match std::fs::remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn read(&self, hash: BlobHash) -> std::io::Result<File> {
+        File::open(self.path_for(&hash)).await
+    }
+}
+
+impl Bowl {
+    // Opt-in: a `Bowl` has no blob store until this is called. Panics if
+    // called twice, since a store swap would orphan the existing refcounts.
+    pub fn enable_blob_store(&self, root: impl Into<PathBuf>) {
+        let mut store = self.blob_store.write();
+        assert!(store.is_none(), "blob store already enabled for this Bowl");
+        *store = Some(Arc::new(BlobStore::new(root)));
+    }
+
+    fn blob_store(&self) -> Arc<BlobStore> {
+        self.blob_store
+            .read()
+            .clone()
+            .expect("enable_blob_store must be called before using blob storage")
+    }
+
+    // Like `add`, but for types that also implement `Blob`: the byte source
+    // is content-hashed and written to the blob store (deduped if an
+    // identical blob already exists) before the metadata entry is added.
+    pub async fn add_with_blob<T, C>(&self, org: &str, value: T) -> std::io::Result<()>
+    where
+        T: Any + crate::MediaTrait<C> + Blob + std::fmt::Debug + Send + Sync + 'static,
+        C: Eq + std::hash::Hash + Clone + std::fmt::Debug,
+    {
+        let store = self.blob_store();
+        let hash = store.write_if_absent(value.blob_bytes()).await?;
+        let key = (
+            TypeId::of::<T>(),
+            value.get_organization().to_string(),
+            value.get_uuid().to_string(),
+        );
+
+        // A routine re-add with unchanged bytes maps to the same hash it
+        // already held — only acquire/release when the binding actually
+        // changes, so refcounts stay keyed off distinct references rather
+        // than call count.
+        let previous_hash = self.blob_refs.read().get(&key).copied();
+        if previous_hash != Some(hash) {
+            store.acquire(hash);
+            self.blob_refs.write().insert(key, hash);
+            if let Some(previous_hash) = previous_hash {
+                store.release(previous_hash)?;
+            }
+        }
+
+        self.add(org, value);
+        Ok(())
+    }
+
+    pub async fn read_blob(&self, hash: BlobHash) -> std::io::Result<impl tokio::io::AsyncRead> {
+        self.blob_store().read(hash).await
+    }
+
+    // Called from `delete` for every type, not just `Blob` ones; entries
+    // that never went through `add_with_blob` simply have no mapping here.
+    pub(crate) fn release_blob_ref(&self, type_id: TypeId, org: &str, uuid: &str) {
+        let key = (type_id, org.to_string(), uuid.to_string());
+        let released = self.blob_refs.write().remove(&key);
+        if let Some(hash) = released {
+            let _ = self.blob_store().release(hash);
+        }
+    }
+}
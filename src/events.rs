@@ -0,0 +1,51 @@
+use std::{any::Any, any::TypeId, collections::HashMap};
+
+use tokio::sync::broadcast;
+
+use crate::Bowl;
+
+// Small enough that a burst of state transitions doesn't get dropped before
+// a subscriber has a chance to poll, generous enough not to matter otherwise.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// What happened to an entry. `StateChanged` carries `Debug`-formatted tags
+/// rather than the state value itself, since the channel is keyed only by
+/// `(TypeId, org)` and has no generic `C` to carry around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BowlEventKind {
+    Added,
+    StateChanged { from_tag: String, to_tag: String },
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BowlEvent {
+    pub uuid: String,
+    pub kind: BowlEventKind,
+}
+
+pub(crate) type EventBus = HashMap<(TypeId, String), broadcast::Sender<BowlEvent>>;
+
+impl Bowl {
+    // Subscribes to every `Added`/`StateChanged`/`Deleted` event for `T`
+    // within `org`. The broadcast sender for this `(TypeId, org)` pair is
+    // created lazily on first subscribe.
+    pub fn subscribe<T: Any + Send + Sync + 'static>(
+        &self,
+        org: &str,
+    ) -> broadcast::Receiver<BowlEvent> {
+        let mut subscribers = self.subscribers.write();
+        let sender = subscribers
+            .entry((TypeId::of::<T>(), org.into()))
+            .or_insert_with(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0);
+        sender.subscribe()
+    }
+
+    // Best-effort: a lagging or absent subscriber never blocks a writer.
+    pub(crate) fn emit<T: Any + Send + Sync + 'static>(&self, org: &str, event: BowlEvent) {
+        let subscribers = self.subscribers.read();
+        if let Some(sender) = subscribers.get(&(TypeId::of::<T>(), org.into())) {
+            let _ = sender.send(event);
+        }
+    }
+}
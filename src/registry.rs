@@ -0,0 +1,120 @@
+use std::{any::Any, any::TypeId, collections::HashMap, hash::Hash};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::MediaTrait;
+
+/// A snapshot is just a flat list of these: one record per tracked entry,
+/// tagged with the stable type name so `restore` knows how to rebuild it.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Record {
+    pub(crate) type_tag: String,
+    pub(crate) org: String,
+    pub(crate) uuid: String,
+    pub(crate) bytes: Vec<u8>,
+}
+
+/// Something restore couldn't place back into the `Bowl`, returned instead
+/// of panicking so schema drift between snapshot and binary is survivable.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Warning {
+    UnknownTypeTag(String),
+    /// The tag was registered, but its bytes no longer match `T`'s current
+    /// layout (e.g. a field was added or removed since the snapshot was
+    /// written).
+    DeserializeFailed(String),
+}
+
+type SerializeFn = fn(&dyn Any) -> Vec<u8>;
+type DeserializeFn = fn(&[u8]) -> Result<Box<dyn Any + Send + Sync>, ()>;
+// Hashes a restored value's state down to `crate::StateKey` (`u64`), so
+// `restore` can rebuild the secondary state index bucket it belongs in
+// without knowing the concrete `T`/`C` at the call site.
+type StateKeyFn = fn(&dyn Any) -> u64;
+
+#[derive(Debug)]
+struct TypeEntry {
+    tag: String,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    state_key: StateKeyFn,
+}
+
+// `contents` can't be serialized directly since it's `Box<dyn Any + Send +
+// Sync>`, so every concrete `T` that should survive a snapshot has to be
+// registered once with a stable string tag plus a serialize/deserialize
+// pair that knows how to downcast to `T`.
+#[derive(Debug, Default)]
+pub struct TypeRegistry {
+    by_type: HashMap<TypeId, TypeEntry>,
+    by_tag: HashMap<String, TypeId>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T, C>(&mut self, tag: impl Into<String>)
+    where
+        T: Any + MediaTrait<C> + Serialize + DeserializeOwned + Send + Sync + 'static,
+        C: Hash,
+    {
+        let tag = tag.into();
+        let type_id = TypeId::of::<T>();
+        self.by_tag.insert(tag.clone(), type_id);
+        self.by_type.insert(
+            type_id,
+            TypeEntry {
+                tag,
+                serialize: serialize_as::<T>,
+                deserialize: deserialize_as::<T>,
+                state_key: state_key_as::<T, C>,
+            },
+        );
+    }
+
+    pub(crate) fn tag_for(&self, type_id: &TypeId) -> Option<&str> {
+        self.by_type.get(type_id).map(|entry| entry.tag.as_str())
+    }
+
+    pub(crate) fn serializer_for(&self, type_id: &TypeId) -> Option<SerializeFn> {
+        self.by_type.get(type_id).map(|entry| entry.serialize)
+    }
+
+    pub(crate) fn deserializer_for(
+        &self,
+        tag: &str,
+    ) -> Option<(TypeId, DeserializeFn, StateKeyFn)> {
+        self.by_tag.get(tag).and_then(|type_id| {
+            self.by_type
+                .get(type_id)
+                .map(|entry| (*type_id, entry.deserialize, entry.state_key))
+        })
+    }
+}
+
+fn serialize_as<T: Any + Serialize>(value: &dyn Any) -> Vec<u8> {
+    let value = value
+        .downcast_ref::<T>()
+        .expect("type registry serializer called with mismatched type");
+    bincode::serialize(value).expect("failed to serialize registered value")
+}
+
+fn deserialize_as<T: Any + DeserializeOwned + Send + Sync + 'static>(
+    bytes: &[u8],
+) -> Result<Box<dyn Any + Send + Sync>, ()> {
+    let value: T = bincode::deserialize(bytes).map_err(|_| ())?;
+    Ok(Box::new(value))
+}
+
+fn state_key_as<T, C>(value: &dyn Any) -> u64
+where
+    T: Any + MediaTrait<C>,
+    C: Hash,
+{
+    let value = value
+        .downcast_ref::<T>()
+        .expect("type registry state-key fn called with mismatched type");
+    crate::state_key(value.get_state())
+}
@@ -0,0 +1,128 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+    thread,
+    time::{Duration, Instant},
+};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+use crate::{Bowl, MediaTrait};
+
+// Rapid-fire editors tend to emit several `Modify` events per save; collapse
+// anything for the same path and event kind within this window into a
+// single update.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Handle returned by `Bowl::watch_dir`. Keeps the underlying `notify`
+/// watcher alive; dropping it stops the watcher and, once its channel
+/// disconnects, the background thread that drives it.
+pub struct WatchHandle {
+    _watcher: RecommendedWatcher,
+}
+
+impl Bowl {
+    // Spawns a background thread that mirrors `path` into `Bowl` entries:
+    // a `Create` event builds a `T` via `factory` and calls `add`, `Modify`
+    // rebuilds and re-adds the entry for a path already being tracked, a
+    // same-directory rename deletes the entry tracked for the old path and
+    // adds a new one built by `factory` for the new path, instead of
+    // falling through to the generic `Modify` handling, and `Remove` looks
+    // the path up in a canonical-path -> uuid side map and calls `delete`.
+    // Dropping the returned `WatchHandle` stops the watch.
+    pub fn watch_dir<T, C>(
+        &self,
+        org: &str,
+        path: impl AsRef<Path>,
+        factory: impl Fn(&Path) -> T + Send + 'static,
+    ) -> notify::Result<WatchHandle>
+    where
+        T: Any + MediaTrait<C> + Debug + Send + Sync + 'static,
+        C: Eq + Hash + Clone + Debug,
+    {
+        let bowl = self.clone();
+        let org = org.to_string();
+        let root = path.as_ref().canonicalize()?;
+
+        let (tx, rx) = channel::<notify::Result<Event>>();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&root, RecursiveMode::NonRecursive)?;
+
+        thread::spawn(move || {
+            let mut uuids_by_path: HashMap<PathBuf, String> = HashMap::new();
+            // Keyed by (path, event-kind discriminant) rather than path
+            // alone: `fs::write` fires `Create` -> `Modify(Data)` ->
+            // `Access(Close)` back-to-back on the same path, and debouncing
+            // by path alone lets a later, genuine event of a *different*
+            // kind (e.g. a `Remove` right after a `Create`) land inside the
+            // window opened by the earlier one and get silently swallowed.
+            let mut last_seen: HashMap<(PathBuf, std::mem::Discriminant<EventKind>), Instant> =
+                HashMap::new();
+
+            for event in rx.iter().flatten() {
+                let kind_id = std::mem::discriminant(&event.kind);
+                // For `RenameMode::Both`, `event.paths` is `[from, to]` in
+                // that order; every other kind carries one path per entry.
+                for (index, changed) in event.paths.iter().enumerate() {
+                    let now = Instant::now();
+                    let debounce_key = (changed.clone(), kind_id);
+                    if let Some(seen) = last_seen.get(&debounce_key) {
+                        if now.duration_since(*seen) < DEBOUNCE {
+                            continue;
+                        }
+                    }
+                    last_seen.insert(debounce_key, now);
+
+                    match event.kind {
+                        EventKind::Create(_) => {
+                            let value = factory(changed);
+                            uuids_by_path.insert(changed.clone(), value.get_uuid().into());
+                            bowl.add(&org, value);
+                        }
+                        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                            if let Some(uuid) = uuids_by_path.remove(changed) {
+                                bowl.delete::<T, C>(&org, &uuid);
+                            }
+                        }
+                        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                            let value = factory(changed);
+                            uuids_by_path.insert(changed.clone(), value.get_uuid().into());
+                            bowl.add(&org, value);
+                        }
+                        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                            if index == 0 {
+                                if let Some(uuid) = uuids_by_path.remove(changed) {
+                                    bowl.delete::<T, C>(&org, &uuid);
+                                }
+                            } else {
+                                let value = factory(changed);
+                                uuids_by_path.insert(changed.clone(), value.get_uuid().into());
+                                bowl.add(&org, value);
+                            }
+                        }
+                        EventKind::Modify(_) if uuids_by_path.contains_key(changed) => {
+                            let value = factory(changed);
+                            uuids_by_path.insert(changed.clone(), value.get_uuid().into());
+                            bowl.add(&org, value);
+                        }
+                        EventKind::Remove(_) => {
+                            if let Some(uuid) = uuids_by_path.remove(changed) {
+                                bowl.delete::<T, C>(&org, &uuid);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        });
+
+        Ok(WatchHandle { _watcher: watcher })
+    }
+}